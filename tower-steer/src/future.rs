@@ -0,0 +1,88 @@
+//! Future types
+
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower_service::Service;
+
+/// Response future for [`Steer`].
+///
+/// [`Steer`]: crate::Steer
+#[pin_project(project = ResponseFutureProj)]
+pub enum ResponseFuture<S, Req>
+where
+    S: Service<Req>,
+{
+    /// The picked `Service` was ready, and is now handling the request.
+    Called {
+        /// The in-flight future returned by the picked `Service`'s `call`.
+        #[pin]
+        fut: S::Future,
+    },
+    /// The picked `Service` wasn't ready when it was picked. Wait for it to become ready, then
+    /// call it.
+    Pending {
+        /// The picked `Service`, cloned out so its readiness can be driven independently.
+        svc: S,
+        /// The request to pass to `svc.call` once it's ready. `None` after that call is made.
+        req: Option<Req>,
+    },
+}
+
+impl<S, Req> ResponseFuture<S, Req>
+where
+    S: Service<Req>,
+{
+    pub(crate) fn called(fut: S::Future) -> Self {
+        ResponseFuture::Called { fut }
+    }
+
+    pub(crate) fn pending(svc: S, req: Req) -> Self {
+        ResponseFuture::Pending {
+            svc,
+            req: Some(req),
+        }
+    }
+}
+
+impl<S, Req> Future for ResponseFuture<S, Req>
+where
+    S: Service<Req>,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        use futures_util::ready;
+
+        loop {
+            match self.as_mut().project() {
+                ResponseFutureProj::Called { fut } => return fut.poll(cx),
+                ResponseFutureProj::Pending { svc, req } => {
+                    ready!(svc.poll_ready(cx))?;
+                    let req = req.take().expect("ResponseFuture polled after completion");
+                    let fut = svc.call(req);
+                    self.set(ResponseFuture::Called { fut });
+                }
+            }
+        }
+    }
+}
+
+impl<S, Req> fmt::Debug for ResponseFuture<S, Req>
+where
+    S: Service<Req> + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponseFuture::Called { .. } => f.debug_struct("ResponseFuture::Called").finish(),
+            ResponseFuture::Pending { svc, .. } => f
+                .debug_struct("ResponseFuture::Pending")
+                .field("svc", svc)
+                .finish(),
+        }
+    }
+}
@@ -54,19 +54,49 @@
 #![warn(missing_debug_implementations)]
 #![allow(clippy::type_complexity)]
 
-use std::{
-    future::Future,
-    pin::Pin,
-    task::{Context, Poll},
-};
+mod future;
+
+use std::{marker::PhantomData, task::{Context, Poll}};
 use tower_service::Service;
 
-type StdError = Box<dyn std::error::Error + Send + Sync + 'static>;
+pub use crate::future::ResponseFuture;
+
+/// Candidate `Service` indices returned by [`Picker::ranked_pick`], most preferred first.
+///
+/// Most [`Picker`]s only ever have a single candidate per request — that's what the default
+/// [`Picker::ranked_pick`] implementation returns — so this avoids heap-allocating a `Vec` for
+/// that common case.
+#[derive(Debug, Clone)]
+pub enum Candidates {
+    /// A single candidate index.
+    One([usize; 1]),
+    /// Multiple candidate indices, most preferred first.
+    Many(Vec<usize>),
+}
+
+impl Candidates {
+    fn as_slice(&self) -> &[usize] {
+        match self {
+            Candidates::One(one) => &one[..],
+            Candidates::Many(many) => &many[..],
+        }
+    }
+}
 
 /// This is how callers of [`Steer`] tell it which `Service` a `Req` corresponds to.
 pub trait Picker<S, Req> {
     /// Return an index into the iterator of `Service` passed to [`Steer::new`].
     fn pick(&mut self, r: &Req, services: &[S]) -> usize;
+
+    /// Return candidate indices into the iterator of `Service` passed to [`Steer::new`], most
+    /// preferred first.
+    ///
+    /// [`Steer`] uses this to fall back to a less-preferred `Service` when a more-preferred one
+    /// isn't ready yet, instead of blocking on it. The default implementation just returns
+    /// [`Picker::pick`]'s index, so implementing `pick` alone is still enough to use a [`Picker`].
+    fn ranked_pick(&mut self, r: &Req, services: &[S]) -> Candidates {
+        Candidates::One([self.pick(r, services)])
+    }
 }
 
 impl<S, F, Req> Picker<S, Req> for F
@@ -78,52 +108,263 @@ where
     }
 }
 
+/// A [`Picker`] that cycles through `Service`s in round-robin order, ignoring the request.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RoundRobin {
+    next: usize,
+}
+
+impl RoundRobin {
+    /// Create a new [`RoundRobin`] picker, starting from the first `Service`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S, Req> Picker<S, Req> for RoundRobin {
+    fn pick(&mut self, _r: &Req, services: &[S]) -> usize {
+        let idx = self.next % services.len();
+        self.next = self.next.wrapping_add(1);
+        idx
+    }
+
+    fn ranked_pick(&mut self, _r: &Req, services: &[S]) -> Candidates {
+        let start = self.next % services.len();
+        self.next = self.next.wrapping_add(1);
+        Candidates::Many((0..services.len()).map(|i| (start + i) % services.len()).collect())
+    }
+}
+
+/// A [`Picker`] that picks `Service`s at random, weighted by a per-`Service` weight.
+///
+/// Useful for distributing load unevenly across a set of `Service`s, e.g. when some shards have
+/// more capacity than others.
+#[derive(Debug, Clone)]
+pub struct WeightedRandom {
+    weights: Vec<u32>,
+}
+
+impl WeightedRandom {
+    /// Create a new [`WeightedRandom`] picker with a weight per `Service`, in the same order the
+    /// `Service`s are passed to [`Steer::new`].
+    ///
+    /// A `Service` with twice the weight of another is picked, on average, twice as often.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weights` is empty, or if every weight is `0`.
+    pub fn new(weights: Vec<u32>) -> Self {
+        assert!(
+            weights.iter().any(|&w| w > 0),
+            "WeightedRandom needs at least one non-zero weight"
+        );
+        Self { weights }
+    }
+
+    /// Add a weight for a `Service` newly added via [`Steer::push`].
+    ///
+    /// The weight is appended, so it applies to the `Service` at the index [`Steer::push`]
+    /// assigned it. Call this every time a `Service` is pushed onto a `Steer` using this picker,
+    /// to keep the weights in sync with the shard list.
+    pub fn push_weight(&mut self, weight: u32) {
+        self.weights.push(weight);
+    }
+
+    /// Remove the weight at `index`, returning it.
+    ///
+    /// Call this with the same `index` passed to [`Steer::remove`], to keep the weights in sync
+    /// with the shard list.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove_weight(&mut self, index: usize) -> u32 {
+        self.weights.remove(index)
+    }
+
+    // Efraimidis-Spirakis weighted random sampling without replacement: give each index a score
+    // of `u^(1/weight)` for a fresh uniform `u`, then sort descending by score. Zero-weighted
+    // services get a score of 0 and always sort last.
+    fn ranked(&self) -> Vec<usize> {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut scored: Vec<(f64, usize)> = self
+            .weights
+            .iter()
+            .enumerate()
+            .map(|(i, &weight)| {
+                let score = if weight == 0 {
+                    0.0
+                } else {
+                    let u: f64 = rng.gen();
+                    u.powf(1.0 / f64::from(weight))
+                };
+                (score, i)
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+        scored.into_iter().map(|(_, i)| i).collect()
+    }
+}
+
+impl<S, Req> Picker<S, Req> for WeightedRandom {
+    /// # Panics
+    ///
+    /// Panics if the number of weights this [`WeightedRandom`] was given doesn't match
+    /// `services.len()`. Use [`WeightedRandom::push_weight`]/[`WeightedRandom::remove_weight`]
+    /// alongside [`Steer::push`]/[`Steer::remove`] to keep them in sync.
+    fn pick(&mut self, r: &Req, services: &[S]) -> usize {
+        self.ranked_pick(r, services).as_slice()[0]
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the number of weights this [`WeightedRandom`] was given doesn't match
+    /// `services.len()`. Use [`WeightedRandom::push_weight`]/[`WeightedRandom::remove_weight`]
+    /// alongside [`Steer::push`]/[`Steer::remove`] to keep them in sync.
+    fn ranked_pick(&mut self, _r: &Req, services: &[S]) -> Candidates {
+        assert_eq!(
+            self.weights.len(),
+            services.len(),
+            "WeightedRandom must have one weight per Service"
+        );
+        Candidates::Many(self.ranked())
+    }
+}
+
 /// `Steer` manages a list of `Service`s which all handle the same type of request.
 ///
 /// An example use case is a sharded service.
 /// It accepts new requests, then:
 /// 1. Determines, via the provided [`Picker`], which `Service` the request coresponds to.
-/// 2. Waits (in `poll_ready`) for *all* services to be ready.
+/// 2. Waits (in `poll_ready`) for the services to be ready.
 /// 3. Calls the correct `Service` with the request, and returns a future corresponding to the
 ///    call.
+///
+/// By default (see [`Steer::new`]), step 2 waits for *all* services to be ready, which causes
+/// head-of-line blocking if any one service is slow to become ready. [`Steer::new_lazy`] builds a
+/// `Steer` that instead reports ready as soon as *any* service is ready, and defers waiting on the
+/// specific service a request is routed to until `call` is invoked for it.
+///
+/// The readiness mode is tracked in the type-level parameter `M` (either [`Eager`] or [`Lazy`]),
+/// rather than as runtime state: lazy mode additionally requires `S: Clone` (to drive a
+/// not-yet-ready `Service`'s readiness from within the returned response future), and eager mode
+/// shouldn't have to pay for that bound.
 #[derive(Debug)]
-pub struct Steer<S, F, Req> {
+pub struct Steer<S, F, Req, M = Eager> {
     router: F,
     // tuple of is_ready, service
     cls: Vec<S>,
     ready: Vec<bool>,
-    _phantom: std::marker::PhantomData<Req>,
+    _marker: PhantomData<(Req, M)>,
 }
 
-impl<S, F, Req> Steer<S, F, Req>
+/// Marker type selecting [`Steer::new`]'s readiness mode: wait for *all* `Service`s to be ready.
+///
+/// This type is never constructed; it only ever appears as [`Steer`]'s type-level mode parameter.
+#[derive(Debug, Clone, Copy)]
+pub enum Eager {}
+
+/// Marker type selecting [`Steer::new_lazy`]'s readiness mode: report ready as soon as *any*
+/// `Service` is ready.
+///
+/// This type is never constructed; it only ever appears as [`Steer`]'s type-level mode parameter.
+#[derive(Debug, Clone, Copy)]
+pub enum Lazy {}
+
+impl<S, F, Req> Steer<S, F, Req, Eager>
 where
-    S: Service<Req, Error = StdError>,
-    S::Future: 'static,
+    S: Service<Req>,
 {
     /// Make a new [`Steer`] with a list of `Service`s and a `Picker`.
     ///
+    /// `poll_ready` will wait for *all* of the `Service`s to be ready, which can cause
+    /// head-of-line blocking if any one `Service` is slow to become ready. See [`Steer::new_lazy`]
+    /// for an alternative that avoids this (at the cost of requiring `S: Clone`).
+    ///
     /// Note: the order of the `Service`s is significant for [`Picker::pick`]'s return value.
     pub fn new(cls: impl IntoIterator<Item = S>, router: F) -> Self {
+        Self::new_inner(cls, router)
+    }
+}
+
+impl<S, F, Req, M> Steer<S, F, Req, M>
+where
+    S: Service<Req>,
+{
+    fn new_inner(cls: impl IntoIterator<Item = S>, router: F) -> Self {
         let cls: Vec<_> = cls.into_iter().collect();
         let ready: Vec<_> = cls.iter().map(|_| false).collect();
         Self {
             router,
             cls,
             ready,
-            _phantom: Default::default(),
+            _marker: PhantomData,
         }
     }
+
+    /// Add a `Service` to the list this [`Steer`] dispatches to.
+    ///
+    /// The new `Service` is appended, so it is assigned the index [`Steer::len`] returned just
+    /// before the call, and is treated as not yet ready until `poll_ready` confirms otherwise.
+    pub fn push(&mut self, svc: S) {
+        self.cls.push(svc);
+        self.ready.push(false);
+    }
+
+    /// Remove the `Service` at `index` from the list this [`Steer`] dispatches to, returning it.
+    ///
+    /// This shifts the index of every `Service` after `index` down by one, same as
+    /// [`Vec::remove`]. A [`Picker`] that hands out indices based on the current service count
+    /// (e.g. round-robin) must account for this when a removal races with its own bookkeeping.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> S {
+        self.ready.remove(index);
+        self.cls.remove(index)
+    }
+
+    /// The number of `Service`s this [`Steer`] currently dispatches to.
+    pub fn len(&self) -> usize {
+        self.cls.len()
+    }
+
+    /// Whether this [`Steer`] has any `Service`s to dispatch to.
+    pub fn is_empty(&self) -> bool {
+        self.cls.is_empty()
+    }
+}
+
+impl<S, F, Req> Steer<S, F, Req, Lazy>
+where
+    S: Service<Req> + Clone,
+{
+    /// Make a new [`Steer`] whose `poll_ready` reports ready as soon as *any* inner `Service` is
+    /// ready, instead of waiting for all of them.
+    ///
+    /// Because the [`Picker`] may route a request to a `Service` that wasn't the one that made
+    /// `poll_ready` return ready, `call` clones out and drives readiness of the picked `Service`
+    /// to completion before dispatching to it, rather than asserting it is already ready. This
+    /// means a slow or backpressured `Service` only blocks requests that are routed to it, not
+    /// requests routed to other, healthy `Service`s.
+    ///
+    /// Note: the order of the `Service`s is significant for [`Picker::pick`]'s return value.
+    pub fn new_lazy(cls: impl IntoIterator<Item = S>, router: F) -> Self {
+        Self::new_inner(cls, router)
+    }
 }
 
-impl<S, Req, T, F> Service<Req> for Steer<S, F, Req>
+impl<S, Req, T, F> Service<Req> for Steer<S, F, Req, Eager>
 where
-    S: Service<Req, Response = T, Error = StdError>,
-    S::Future: 'static,
+    S: Service<Req, Response = T>,
     F: Picker<S, Req>,
 {
     type Response = T;
-    type Error = StdError;
-    type Future = Pin<Box<dyn Future<Output = Result<T, StdError>>>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S, Req>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         use futures_util::ready;
@@ -142,12 +383,228 @@ where
     }
 
     fn call(&mut self, req: Req) -> Self::Future {
-        let idx = self.router.pick(&req, &self.cls[..]);
-        let ready = &mut self.ready[idx];
+        let candidates = self.router.ranked_pick(&req, &self.cls[..]);
+        let candidates = candidates.as_slice();
+        assert!(
+            !candidates.is_empty(),
+            "Picker::ranked_pick must return at least one candidate"
+        );
+
+        let idx = candidates[0];
+        let is_ready = &mut self.ready[idx];
+        assert!(*is_ready);
         let cl = &mut self.cls[idx];
-        assert!(*ready);
         let fut = cl.call(req);
-        *ready = false;
-        Box::pin(fut)
+        *is_ready = false;
+        ResponseFuture::called(fut)
+    }
+}
+
+impl<S, Req, T, F> Service<Req> for Steer<S, F, Req, Lazy>
+where
+    S: Service<Req, Response = T> + Clone,
+    F: Picker<S, Req>,
+{
+    type Response = T;
+    type Error = S::Error;
+    type Future = ResponseFuture<S, Req>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // with no services to wait on, there's nothing that could ever wake us: report ready
+        // immediately, same as `Eager`, rather than returning `Pending` forever.
+        if self.cls.is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+
+        // report ready as soon as *any* service is ready, tracking progress on the rest so we
+        // don't re-poll services we already know are ready.
+        let mut any_ready = false;
+        for (serv, is_ready) in self.cls.iter_mut().zip(self.ready.iter_mut()) {
+            if *is_ready {
+                any_ready = true;
+                continue;
+            }
+
+            if let Poll::Ready(res) = serv.poll_ready(cx) {
+                res?;
+                *is_ready = true;
+                any_ready = true;
+            }
+        }
+
+        if any_ready {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let candidates = self.router.ranked_pick(&req, &self.cls[..]);
+        let candidates = candidates.as_slice();
+        assert!(
+            !candidates.is_empty(),
+            "Picker::ranked_pick must return at least one candidate"
+        );
+
+        if let Some(&idx) = candidates.iter().find(|&&idx| self.ready[idx]) {
+            self.ready[idx] = false;
+            let fut = self.cls[idx].call(req);
+            ResponseFuture::called(fut)
+        } else {
+            // None of the candidates were ready. Clone the most-preferred one out and drive its
+            // readiness the rest of the way before calling it, so only this request blocks on it
+            // instead of every request `Steer` handles.
+            let idx = candidates[0];
+            let cl = self.cls[idx].clone();
+            ResponseFuture::pending(cl, req)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        future::Future,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    };
+
+    #[derive(Clone)]
+    struct MockService {
+        id: usize,
+        ready: Arc<AtomicBool>,
+    }
+
+    impl MockService {
+        fn new(id: usize, ready: bool) -> Self {
+            Self {
+                id,
+                ready: Arc::new(AtomicBool::new(ready)),
+            }
+        }
+
+        fn set_ready(&self, ready: bool) {
+            self.ready.store(ready, Ordering::SeqCst);
+        }
+    }
+
+    impl Service<()> for MockService {
+        type Response = usize;
+        type Error = std::convert::Infallible;
+        type Future = futures_util::future::Ready<Result<usize, Self::Error>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.ready.load(Ordering::SeqCst) {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&mut self, _req: ()) -> Self::Future {
+            futures_util::future::ready(Ok(self.id))
+        }
+    }
+
+    fn noop_cx() -> Context<'static> {
+        let waker = futures_util::task::noop_waker();
+        Context::from_waker(Box::leak(Box::new(waker)))
+    }
+
+    #[test]
+    fn lazy_poll_ready_reports_ready_with_one_pending() {
+        let a = MockService::new(0, true);
+        let b = MockService::new(1, false);
+        let mut steer = Steer::new_lazy(vec![a, b], |_: &(), _: &[_]| 0);
+
+        let mut cx = noop_cx();
+        assert!(steer.poll_ready(&mut cx).is_ready());
+    }
+
+    #[test]
+    fn lazy_call_waits_for_picked_service_when_not_ready() {
+        let a = MockService::new(0, true);
+        let b = MockService::new(1, false);
+        let b_handle = b.clone();
+        // always route to index 1, which starts out not ready.
+        let mut steer = Steer::new_lazy(vec![a, b], |_: &(), _: &[_]| 1);
+
+        let mut cx = noop_cx();
+        // `a` being ready is enough for `poll_ready` to report ready, even though the request
+        // will be routed to `b`.
+        assert!(steer.poll_ready(&mut cx).is_ready());
+
+        let mut fut = Box::pin(steer.call(()));
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+
+        b_handle.set_ready(true);
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(Ok(id)) => assert_eq!(id, 1),
+            other => panic!("expected Ready(Ok(1)), got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn push_and_remove_shift_indices() {
+        let mut steer = Steer::new(
+            vec![MockService::new(0, true), MockService::new(1, true)],
+            |_: &(), _: &[_]| 0,
+        );
+        assert_eq!(steer.len(), 2);
+
+        steer.push(MockService::new(2, true));
+        assert_eq!(steer.len(), 3);
+
+        let removed = steer.remove(0);
+        assert_eq!(removed.id, 0);
+        assert_eq!(steer.len(), 2);
+
+        // the service that used to be at index 1 (id 1) has shifted down to index 0, matching
+        // Vec::remove's semantics.
+        let mut cx = noop_cx();
+        assert!(steer.poll_ready(&mut cx).is_ready());
+        let mut fut = Box::pin(steer.call(()));
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(Ok(id)) => assert_eq!(id, 1),
+            other => panic!("expected Ready(Ok(1)), got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn round_robin_ranked_pick_rotates_and_wraps() {
+        let mut rr = RoundRobin::new();
+        let services: Vec<()> = vec![(), (), ()];
+
+        // one full lap: starts at 0 and advances by one each call.
+        for expected_start in 0..3 {
+            let candidates = rr.ranked_pick(&(), &services);
+            assert_eq!(candidates.as_slice()[0], expected_start);
+        }
+
+        // wraps back around to 0 on the next lap.
+        let candidates = rr.ranked_pick(&(), &services);
+        assert_eq!(candidates.as_slice()[0], 0);
+    }
+
+    #[test]
+    fn weighted_random_never_picks_a_zero_weight_service_over_a_weighted_one() {
+        let services: Vec<()> = vec![(), (), ()];
+        let mut wr = WeightedRandom::new(vec![0, 5, 0]);
+
+        for _ in 0..100 {
+            assert_eq!(wr.pick(&(), &services), 1);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "WeightedRandom must have one weight per Service")]
+    fn weighted_random_panics_on_weight_service_length_mismatch() {
+        let services: Vec<()> = vec![(), (), ()];
+        let mut wr = WeightedRandom::new(vec![1, 1]);
+        wr.pick(&(), &services);
     }
 }